@@ -0,0 +1,162 @@
+//! Recomputes a block's hash from its header, so a faulty or malicious RPC
+//! endpoint can't pass off a mismatched `hash` for the header it served.
+
+use rlp::RlpStream;
+use tiny_keccak::{Hasher, Keccak};
+use web3::types::{Block, H256};
+
+/// Recomputes the Keccak-256 hash of the RLP-encoded header of `block`.
+pub fn compute_block_hash<TX>(block: &Block<TX>) -> H256 {
+	keccak256(&encode_header(block))
+}
+
+/// RLP-encodes the header of `block`, appending each fork-dependent field
+/// (EIP-1559's `base_fee_per_gas`, Shanghai's `withdrawals_root`, Cancun's
+/// `blob_gas_used`/`excess_blob_gas`/`parent_beacon_block_root`, Prague's
+/// `requests_hash`) only when the provider populated it, in the order each
+/// was added to the header.
+fn encode_header<TX>(block: &Block<TX>) -> Vec<u8> {
+	let has_base_fee = block.base_fee_per_gas.is_some();
+	let has_withdrawals_root = block.withdrawals_root.is_some();
+	let has_blob_gas = block.blob_gas_used.is_some() && block.excess_blob_gas.is_some();
+	let has_parent_beacon_root = block.parent_beacon_block_root.is_some();
+	let has_requests_hash = block.requests_hash.is_some();
+
+	let field_count = 15
+		+ has_base_fee as usize
+		+ has_withdrawals_root as usize
+		+ has_blob_gas as usize * 2
+		+ has_parent_beacon_root as usize
+		+ has_requests_hash as usize;
+
+	let mut stream = RlpStream::new();
+	stream.begin_list(field_count);
+	stream.append(&block.parent_hash);
+	stream.append(&block.uncles_hash);
+	stream.append(&block.author);
+	stream.append(&block.state_root);
+	stream.append(&block.transactions_root);
+	stream.append(&block.receipts_root);
+	stream.append(&block.logs_bloom.unwrap_or_default());
+	stream.append(&block.difficulty);
+	stream.append(&block.number.unwrap_or_default());
+	stream.append(&block.gas_limit);
+	stream.append(&block.gas_used);
+	stream.append(&block.timestamp);
+	stream.append(&block.extra_data.0);
+	stream.append(&block.mix_hash.unwrap_or_default());
+	stream.append(&block.nonce.unwrap_or_default());
+	if let Some(base_fee_per_gas) = block.base_fee_per_gas {
+		stream.append(&base_fee_per_gas);
+	}
+	if let Some(withdrawals_root) = block.withdrawals_root {
+		stream.append(&withdrawals_root);
+	}
+	if has_blob_gas {
+		stream.append(&block.blob_gas_used.unwrap());
+		stream.append(&block.excess_blob_gas.unwrap());
+	}
+	if let Some(parent_beacon_block_root) = block.parent_beacon_block_root {
+		stream.append(&parent_beacon_block_root);
+	}
+	if let Some(requests_hash) = block.requests_hash {
+		stream.append(&requests_hash);
+	}
+
+	stream.out().to_vec()
+}
+
+fn keccak256(bytes: &[u8]) -> H256 {
+	let mut hasher = Keccak::v256();
+	let mut output = [0u8; 32];
+	hasher.update(bytes);
+	hasher.finalize(&mut output);
+	H256::from(output)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rlp::Rlp;
+	use web3::types::{Bytes, H160, H2048, H64, U256, U64};
+
+	fn base_block() -> Block<H256> {
+		Block {
+			hash: None,
+			parent_hash: H256::zero(),
+			uncles_hash: H256::zero(),
+			author: H160::zero(),
+			state_root: H256::zero(),
+			transactions_root: H256::zero(),
+			receipts_root: H256::zero(),
+			number: Some(U64::from(1u64)),
+			gas_used: U256::zero(),
+			gas_limit: U256::zero(),
+			extra_data: Bytes::default(),
+			logs_bloom: Some(H2048::zero()),
+			timestamp: U256::zero(),
+			difficulty: U256::zero(),
+			total_difficulty: None,
+			seal_fields: vec![],
+			uncles: vec![],
+			transactions: vec![],
+			size: None,
+			mix_hash: Some(H256::zero()),
+			nonce: Some(H64::zero()),
+			base_fee_per_gas: None,
+			withdrawals_root: None,
+			withdrawals: None,
+			parent_beacon_block_root: None,
+			blob_gas_used: None,
+			excess_blob_gas: None,
+			requests_hash: None,
+		}
+	}
+
+	#[test]
+	fn test_encode_header_pre_shanghai_has_15_fields() {
+		let bytes = encode_header(&base_block());
+		assert_eq!(Rlp::new(&bytes).item_count().unwrap(), 15);
+	}
+
+	/// Regression test for a mainnet header shape that has been the norm
+	/// since the Shanghai upgrade (April 2023): every live block carries a
+	/// `withdrawals_root`, so it must be part of the encoded header or the
+	/// recomputed hash never matches the provider's.
+	#[test]
+	fn test_encode_header_includes_withdrawals_root_post_shanghai() {
+		let mut block = base_block();
+		block.base_fee_per_gas = Some(U256::from(7u64));
+		block.withdrawals_root = Some(H256::repeat_byte(0xab));
+
+		let bytes = encode_header(&block);
+		let rlp = Rlp::new(&bytes);
+		assert_eq!(rlp.item_count().unwrap(), 17);
+		assert_eq!(rlp.at(15).unwrap().as_val::<U256>().unwrap(), block.base_fee_per_gas.unwrap());
+		assert_eq!(rlp.at(16).unwrap().as_val::<H256>().unwrap(), block.withdrawals_root.unwrap());
+	}
+
+	/// Regression test for Cancun (blob fields, beacon root) and Prague
+	/// (`requests_hash`) header fields.
+	#[test]
+	fn test_encode_header_includes_cancun_and_prague_fields() {
+		let mut block = base_block();
+		block.base_fee_per_gas = Some(U256::from(7u64));
+		block.withdrawals_root = Some(H256::repeat_byte(1));
+		block.blob_gas_used = Some(U64::from(2u64));
+		block.excess_blob_gas = Some(U64::from(3u64));
+		block.parent_beacon_block_root = Some(H256::repeat_byte(4));
+		block.requests_hash = Some(H256::repeat_byte(5));
+
+		let bytes = encode_header(&block);
+		let rlp = Rlp::new(&bytes);
+		assert_eq!(rlp.item_count().unwrap(), 21);
+		assert_eq!(rlp.at(17).unwrap().as_val::<U64>().unwrap(), block.blob_gas_used.unwrap());
+		assert_eq!(rlp.at(18).unwrap().as_val::<U64>().unwrap(), block.excess_blob_gas.unwrap());
+		assert_eq!(
+			rlp.at(19).unwrap().as_val::<H256>().unwrap(),
+			block.parent_beacon_block_root.unwrap()
+		);
+		assert_eq!(rlp.at(20).unwrap().as_val::<H256>().unwrap(), block.requests_hash.unwrap());
+	}
+}
@@ -1,6 +1,13 @@
 use std::{collections::VecDeque, fmt};
 
+mod header_hash;
+mod pool;
+mod store;
+mod units;
+
 use futures::StreamExt;
+use pool::PoolConfig;
+use store::{SledStore, Store};
 use web3::{
 	contract::Contract,
 	ethabi::{Event, Log, RawLog},
@@ -9,6 +16,58 @@ use web3::{
 	Web3,
 };
 
+/// An EIP-1898 block reference: either a plain block number or a block hash
+/// with an explicit canonical-chain requirement.
+///
+/// When `require_canonical` is set on a `Hash` reference, the caller expects
+/// `fetch_block_queue` to verify that the referenced hash is still the one
+/// the chain associates with that block's number before trusting it.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum BlockReference {
+	Number(U64),
+	Hash { hash: H256, require_canonical: bool },
+}
+
+impl BlockReference {
+	fn as_block_id(&self) -> BlockId {
+		match self {
+			BlockReference::Number(number) => BlockId::Number(BlockNumber::Number(*number)),
+			BlockReference::Hash { hash, .. } => BlockId::Hash(*hash),
+		}
+	}
+
+	fn require_canonical(&self) -> bool {
+		matches!(self, BlockReference::Hash { require_canonical: true, .. })
+	}
+}
+
+/// Errors that can occur while fetching and validating blocks for the reorg
+/// queue. These are surfaced instead of panicking so that callers can decide
+/// how to react to a chain that moved out from under them.
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+	#[error("block {0:?} was not found")]
+	BlockNotFound(BlockReference),
+
+	#[error("requested block by hash {requested:#x} but provider returned a block hashed {returned:#x}")]
+	UnexpectedHash { requested: H256, returned: H256 },
+
+	#[error("block {hash:#x} is no longer canonical: block {number} now resolves to {canonical_hash:#x}")]
+	NonCanonical { hash: H256, number: U64, canonical_hash: H256 },
+
+	#[error("block {number} hash {claimed_hash:#x} does not match its recomputed header hash {computed_hash:#x}")]
+	BlockIntegrity { number: U64, claimed_hash: H256, computed_hash: H256 },
+
+	#[error("could not find a common ancestor with the persisted chain")]
+	NoCommonAncestor,
+
+	#[error("web3 request failed: {0}")]
+	Web3(#[from] web3::Error),
+
+	#[error("storage error: {0}")]
+	Store(#[from] store::StoreError),
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct Block {
 	pub number: U64,
@@ -16,23 +75,25 @@ pub struct Block {
 	pub parsed_logs: Vec<ParsedLog>,
 }
 
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ParsedLog {
+	pub pool_address: H160,
 	pub sender: String,
 	pub receiver: String,
 	pub direction: String,
-	pub amount_usdc: String,
-	pub amount_dai: String,
+	pub amount0: String,
+	pub amount1: String,
 }
 
 impl fmt::Debug for ParsedLog {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f, "Parsed Log: {{\n")?;
+		write!(f, " pool_address: {:#x}\n", self.pool_address)?;
 		write!(f, " sender: {}\n", self.sender)?;
 		write!(f, " receiver: {}\n", self.receiver)?;
 		write!(f, " direction: {}\n", self.direction)?;
-		write!(f, " amount_usdc: {:}\n", self.amount_usdc)?;
-		write!(f, " amount_dai: {:}\n", self.amount_dai)?;
+		write!(f, " amount0: {:}\n", self.amount0)?;
+		write!(f, " amount1: {:}\n", self.amount1)?;
 		write!(f, "}}")
 	}
 }
@@ -45,14 +106,22 @@ async fn main() -> Result<(), anyhow::Error> {
 	assert!(BLOCK_REORG_MAX_DEPTH > 0, "BLOCK_REORG_MAX_DEPTH should be set larger than 0");
 
 	let websocket_infura_endpoint: String = std::env::var("INFURA_WSS_ENDPOINT")?;
+	let pool_config_path: String = std::env::var("POOL_CONFIG_PATH")?;
+	let store_path: String = std::env::var("STORE_PATH")?;
 
 	let web3 =
 		web3::Web3::new(web3::transports::ws::WebSocket::new(&websocket_infura_endpoint).await?);
-	let contract_address =
-		H160::from_slice(&hex::decode("5777d92f208679db4b9778590fa3cab3ac9e2168").unwrap()[..]);
+
+	let pools = pool::load_pool_configs(&pool_config_path)?;
+	let pool_addresses: Vec<H160> = pools.iter().map(|pool| pool.address).collect();
+
+	let mut store = SledStore::open(&store_path)?;
+
+	// All the pools we index are Uniswap v3 pools, so a single pool's ABI
+	// (and its `Swap` event) is enough to decode logs from every pool.
 	let contract = Contract::from_json(
 		web3.eth(),
-		contract_address,
+		pool_addresses[0],
 		include_bytes!("contracts/uniswap_pool_abi.json"),
 	)?;
 	let swap_event = contract.abi().events_by_name("Swap")?.first().unwrap();
@@ -60,205 +129,296 @@ async fn main() -> Result<(), anyhow::Error> {
 
 	let mut block_stream = web3.eth_subscribe().subscribe_new_heads().await?;
 
+	let (reorg_events_tx, mut reorg_events_rx) = tokio::sync::mpsc::channel::<ReorgEvent>(16);
+	tokio::spawn(async move {
+		while let Some(event) = reorg_events_rx.recv().await {
+			match event {
+				ReorgEvent::Replaced(blocks) => {
+					let numbers: Vec<U64> = blocks.iter().map(|b| b.number).collect();
+					println!("reorg: replaced blocks {:?}", numbers);
+				},
+				ReorgEvent::DeepReorg { from_number } => {
+					println!("reorg: deeper than tracked depth, starting from block {}", from_number);
+				},
+			}
+		}
+	});
+
 	let mut queue = VecDeque::<Block>::new();
 
 	if let Some(Ok(block)) = block_stream.next().await {
 		let current_block_num = block.number.expect("Error getting the current block number");
+		let queue_start = current_block_num - U64::from(BLOCK_REORG_MAX_DEPTH as u64 - 2);
+
+		// Backfill the gap between the last block we persisted before a
+		// restart and the live reorg window, so a restart doesn't silently
+		// drop swaps that happened while the process was down.
+		if let Some(last_persisted) = store.highest_block_number()? {
+			let resume_from = last_persisted + U64::from(1u64);
+			if resume_from < queue_start {
+				let backfilled = fetch_block_range(
+					resume_from,
+					queue_start - U64::from(1u64),
+					web3.clone(),
+					&pools,
+					swap_event_signature,
+					swap_event.clone(),
+				)
+				.await?;
+
+				for block in &backfilled {
+					store.put_block(block.number, block.hash, &block.parsed_logs)?;
+				}
+			}
+		}
 
-		let block_numbers: Vec<U64> = (0..BLOCK_REORG_MAX_DEPTH - 1)
+		let block_references: Vec<BlockReference> = (0..BLOCK_REORG_MAX_DEPTH - 1)
 			.rev()
-			.map(|x| current_block_num - U64::from(x))
+			.map(|x| BlockReference::Number(current_block_num - U64::from(x)))
 			.collect();
 
 		queue = fetch_block_queue(
-			block_numbers,
+			block_references,
 			web3.clone(),
-			contract_address,
+			&pools,
 			swap_event_signature,
 			swap_event.clone(),
 		)
-		.await;
+		.await?;
 	}
 
 	while let Some(Ok(block)) = block_stream.next().await {
 		let current_block_num = block.number.expect("Error getting the current block number");
+		let queue_len_before_push = queue.len();
 
-		let mut block_numbers = queue.iter().map(|block| block.number).collect::<Vec<U64>>();
-		block_numbers.push(current_block_num);
+		let mut block_references =
+			queue.iter().map(|block| BlockReference::Number(block.number)).collect::<Vec<_>>();
+		block_references.push(BlockReference::Number(current_block_num));
 
 		let new_queue = fetch_block_queue(
-			block_numbers,
+			block_references,
 			web3.clone(),
-			contract_address,
+			&pools,
 			swap_event_signature,
 			swap_event.clone(),
 		)
-		.await;
+		.await?;
 
 		queue.push_back(new_queue[new_queue.len() - 1].clone());
 
 		assert_eq!(
 			queue.len(),
-			BLOCK_REORG_MAX_DEPTH,
-			"`queue` should have length {} at this point.",
-			BLOCK_REORG_MAX_DEPTH
+			queue_len_before_push + 1,
+			"`queue` should have grown by exactly one block at this point."
 		);
 		assert_eq!(
 			new_queue.len(),
-			BLOCK_REORG_MAX_DEPTH,
-			"`new_queue` should have length {} at this point.",
-			BLOCK_REORG_MAX_DEPTH
+			queue.len(),
+			"`new_queue` should cover the same blocks as `queue` at this point."
 		);
 
-		let reorganizations = check_and_update_queue(&mut queue, &new_queue);
+		match check_and_update_queue(&mut queue, &new_queue) {
+			ReorgOutcome::Shallow { depth, replaced_blocks } => {
+				if !replaced_blocks.is_empty() {
+					reorg_events_tx.send(ReorgEvent::Replaced(replaced_blocks.clone())).await.ok();
+				}
 
-		let block = queue.pop_front().expect("fail in popping element from the queue");
+				// Only overwrite entries that are already persisted; blocks still
+				// inside the reorg window are stored once they are popped.
+				for replaced in &replaced_blocks {
+					if store.get_block(replaced.number)?.is_some() {
+						store.put_block(replaced.number, replaced.hash, &replaced.parsed_logs)?;
+					}
+				}
 
-		println!("block: {} reorgs: {}", block.number, reorganizations);
-		if block.parsed_logs.len() > 0 {
-			println!("{:#?}", block.parsed_logs);
-		}
+				let block = queue.pop_front().expect("fail in popping element from the queue");
 
-		assert_eq!(
-			queue.len(),
-			BLOCK_REORG_MAX_DEPTH - 1,
-			"`queue` should have length {} at this point.",
-			BLOCK_REORG_MAX_DEPTH - 1
-		);
+				store.put_block(block.number, block.hash, &block.parsed_logs)?;
+
+				println!("block: {} reorgs: {}", block.number, depth);
+				if block.parsed_logs.len() > 0 {
+					println!("{:#?}", block.parsed_logs);
+				}
+			},
+			ReorgOutcome::DeeperThanTracked { from_number } => {
+				reorg_events_tx.send(ReorgEvent::DeepReorg { from_number }).await.ok();
+
+				let ancestor_number = find_common_ancestor(&web3, &store, from_number).await?;
+
+				let mut rebuilt_queue = fetch_block_range(
+					ancestor_number + U64::from(1u64),
+					current_block_num,
+					web3.clone(),
+					&pools,
+					swap_event_signature,
+					swap_event.clone(),
+				)
+				.await?;
+
+				// Everything older than the live reorg window is now
+				// confirmed by having walked back to a common ancestor, so
+				// persist it as final and keep only the trailing window.
+				while rebuilt_queue.len() > BLOCK_REORG_MAX_DEPTH - 1 {
+					let finalized =
+						rebuilt_queue.pop_front().expect("rebuilt queue should not be empty");
+					store.put_block(finalized.number, finalized.hash, &finalized.parsed_logs)?;
+				}
+
+				queue = rebuilt_queue;
+			},
+		}
 	}
 
 	Ok(())
 }
 
 pub async fn fetch_block_queue(
-	block_numbers: Vec<U64>,
+	block_references: Vec<BlockReference>,
 	web3: Web3<WebSocket>,
-	contract_address: H160,
+	pools: &[PoolConfig],
 	swap_event_signature: H256,
 	swap_event: Event,
-) -> VecDeque<Block> {
+) -> Result<VecDeque<Block>, FetchError> {
 	let mut queue = VecDeque::<Block>::new();
 
-	for block_i in block_numbers {
+	for block_ref in block_references {
 		let block = web3
 			.eth()
-			.block(BlockId::Number(BlockNumber::Number(block_i)))
-			.await
-			.unwrap()
-			.unwrap();
+			.block(block_ref.as_block_id())
+			.await?
+			.ok_or(FetchError::BlockNotFound(block_ref))?;
+
+		let hash = block.hash.ok_or(FetchError::BlockNotFound(block_ref))?;
+		let number = block.number.ok_or(FetchError::BlockNotFound(block_ref))?;
+
+		if let BlockReference::Hash { hash: requested, .. } = block_ref {
+			if hash != requested {
+				return Err(FetchError::UnexpectedHash { requested, returned: hash })
+			}
+		}
+
+		let computed_hash = header_hash::compute_block_hash(&block);
+		if computed_hash != hash {
+			return Err(FetchError::BlockIntegrity { number, claimed_hash: hash, computed_hash })
+		}
+
+		if block_ref.require_canonical() {
+			let canonical_block = web3
+				.eth()
+				.block(BlockId::Number(BlockNumber::Number(number)))
+				.await?
+				.ok_or(FetchError::BlockNotFound(block_ref))?;
+			let canonical_hash =
+				canonical_block.hash.ok_or(FetchError::BlockNotFound(block_ref))?;
+
+			if canonical_hash != hash {
+				return Err(FetchError::NonCanonical { hash, number, canonical_hash })
+			}
+		}
+
+		if let BlockReference::Number(expected_number) = block_ref {
+			assert_eq!(
+				expected_number, number,
+				"block_ref should equal `number` field of block fetched"
+			);
+		}
 
 		let swap_logs_in_block = web3
 			.eth()
 			.logs(
 				web3::types::FilterBuilder::default()
-					.block_hash(block.hash.unwrap())
-					.address(vec![contract_address])
+					.block_hash(hash)
+					.address(pools.iter().map(|pool| pool.address).collect())
 					.topics(Some(vec![swap_event_signature]), None, None, None)
 					.build(),
 			)
-			.await
-			.unwrap();
+			.await?;
 
 		let mut parsed_logs = vec![];
 		for log in swap_logs_in_block {
-			let log =
+			let pool = pools
+				.iter()
+				.find(|pool| pool.address == log.address)
+				.expect("log address should match one of the configured pools");
+
+			let parsed_log =
 				swap_event.parse_log(RawLog { topics: log.topics, data: log.data.0 }).unwrap();
 
-			parsed_logs.push(parse_log(log));
+			parsed_logs.push(parse_log(parsed_log, pool));
 		}
 
-		assert_eq!(
-			block_i,
-			block.number.expect("could not get block number"),
-			"block_i should equal `number` field of block fetched"
-		);
-
-		let hash = block.hash.expect("could not get block number");
-		let number = block_i;
-
 		queue.push_back(Block { hash, number, parsed_logs });
 	}
-	queue
+	Ok(queue)
 }
 
 pub fn u256_is_negative(amount: U256) -> bool {
 	amount.bit(255)
 }
 
-pub fn u256_to_string(amount: U256, decimals: usize) -> String {
-	let mut amount = amount;
-
-	if u256_is_negative(amount) {
-		// We compute the 2's complement
-		let mut bytes = [0u8; 32];
-		amount.to_big_endian(&mut bytes);
-
-		for b in bytes.iter_mut() {
-			*b = !(*b);
-		}
-
-		amount = U256::from_big_endian(&bytes);
-		amount += U256::one();
-	}
-
-	let decimal_string = amount.to_string();
-
-	let integer: String = match decimal_string.clone().len() > decimals {
-		true => decimal_string[..decimal_string.len() - decimals].to_string(),
-		false => "0".to_string(),
-	};
-
-	let decimals: String = match decimal_string.len() > decimals {
-		true =>
-			if decimals > 0 {
-				decimal_string[decimal_string.len() - decimals..].to_string()
-			} else {
-				"0".to_string()
-			},
-		false => {
-			format!("{}{}", "0".repeat(decimals - decimal_string.len()), &decimal_string[..])
-		},
-	};
-
-	format!("{}.{}", integer, decimals)
-}
-
 fn address_to_string(address: H160) -> String {
 	let mut a = String::from("0x");
 	a.push_str(hex::encode(&address).as_str());
 	a
 }
 
-pub fn parse_log(log: Log) -> ParsedLog {
+pub fn parse_log(log: Log, pool: &PoolConfig) -> ParsedLog {
 	let sender = address_to_string(log.params[0].value.clone().into_address().unwrap());
 	let receiver = address_to_string(log.params[1].value.clone().into_address().unwrap());
 
-	let amount_dai = log.params[2].value.clone().into_int().unwrap();
-	let amount_usdc = log.params[3].value.clone().into_int().unwrap();
+	let amount0 = log.params[2].value.clone().into_int().unwrap();
+	let amount1 = log.params[3].value.clone().into_int().unwrap();
 
 	// check the sign of each amount looking at the last bit (true = negative, false = positive)
-	let is_amount_dai_negative = amount_dai.bit(255);
-	let is_amount_usdc_negative = amount_usdc.bit(255);
+	let is_amount0_negative = amount0.bit(255);
+	let is_amount1_negative = amount1.bit(255);
 
 	// one should be false and the other true
-	assert!(is_amount_dai_negative ^ is_amount_usdc_negative);
+	assert!(is_amount0_negative ^ is_amount1_negative);
 
 	// the negative one is the swap's output
-	let direction =
-		if is_amount_usdc_negative { "DAI -> USDC".to_string() } else { "USDC -> DAI".to_string() };
+	let direction = if is_amount1_negative {
+		format!("{} -> {}", pool.token0_symbol, pool.token1_symbol)
+	} else {
+		format!("{} -> {}", pool.token1_symbol, pool.token0_symbol)
+	};
 
 	// format the amount according to the decimals of each token
-	let amount_dai = u256_to_string(amount_dai, 18);
-	let amount_usdc = u256_to_string(amount_usdc, 6);
+	let amount0 = units::format_units_signed(amount0, pool.token0_decimals);
+	let amount1 = units::format_units_signed(amount1, pool.token1_decimals);
 
-	ParsedLog { sender, receiver, direction, amount_usdc, amount_dai }
+	ParsedLog { pool_address: pool.address, sender, receiver, direction, amount0, amount1 }
+}
+
+/// Outcome of comparing `queue` against a freshly fetched `new_queue`.
+///
+/// A `Shallow` reorg is one the queue's tracked depth can absorb: the
+/// affected blocks are simply swapped in place. A `DeeperThanTracked` reorg
+/// means even the oldest tracked block changed, so the caller has to widen
+/// its search past the queue to find a still-valid common ancestor.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReorgOutcome {
+	Shallow { depth: u32, replaced_blocks: Vec<Block> },
+	DeeperThanTracked { from_number: U64 },
+}
+
+/// Notification sent over the reorg channel so that downstream consumers
+/// (the storage subsystem, a printer, ...) can react to rollbacks and
+/// re-applications without being woven into the main indexing loop.
+#[derive(Debug, Clone)]
+pub enum ReorgEvent {
+	Replaced(Vec<Block>),
+	DeepReorg { from_number: U64 },
 }
 
 /// This function updates the main queue using a new queue fetched some blocks ahead of time.
 /// Normally the new block is constructed 1 block ahead of time. For example: `queue` has
 /// information of blocks 1,2,3,4,5 fetched at the moment block 5 was detected. Then, `new_queue`
 /// has the information of the same blocks (1,2,3,4,5) but fetched at block 6 or after.
-pub fn check_and_update_queue(queue: &mut VecDeque<Block>, new_queue: &VecDeque<Block>) -> u32 {
+pub fn check_and_update_queue(
+	queue: &mut VecDeque<Block>,
+	new_queue: &VecDeque<Block>,
+) -> ReorgOutcome {
 	assert_eq!(
 		queue.len(),
 		new_queue.len(),
@@ -269,12 +429,11 @@ pub fn check_and_update_queue(queue: &mut VecDeque<Block>, new_queue: &VecDeque<
 		"Block number of front element in both queues doesn't coincide"
 	);
 	if queue[0].hash != new_queue[0].hash {
-		println!("queue: {:#?}", queue);
-		println!("new_queue: {:#?}", new_queue);
-		panic!("A {}-blocks reorganization ocurred", queue.len());
+		return ReorgOutcome::DeeperThanTracked { from_number: queue[0].number }
 	}
 
 	let mut reorganizations = 0;
+	let mut replaced_blocks = vec![];
 	for (i, q) in queue.iter_mut().enumerate().rev() {
 		assert_eq!(q.number, new_queue[i].number, "Block numbers on both queues doesn't coincide.");
 
@@ -282,10 +441,64 @@ pub fn check_and_update_queue(queue: &mut VecDeque<Block>, new_queue: &VecDeque<
 			break
 		}
 		*q = new_queue[i].clone();
+		replaced_blocks.push(q.clone());
 
 		reorganizations += 1;
 	}
-	reorganizations
+	ReorgOutcome::Shallow { depth: reorganizations, replaced_blocks }
+}
+
+/// Walks backwards from `from_number` looking for a block number whose
+/// persisted hash still matches what the chain reports, i.e. a common
+/// ancestor that survived the reorg. Used when a reorg is deeper than
+/// `BLOCK_REORG_MAX_DEPTH` and the live queue no longer has a trustworthy
+/// starting point. Returns that block's number.
+pub async fn find_common_ancestor(
+	web3: &Web3<WebSocket>,
+	store: &dyn Store,
+	from_number: U64,
+) -> Result<U64, FetchError> {
+	let mut candidate_number = from_number;
+
+	while candidate_number > U64::zero() {
+		candidate_number = candidate_number - U64::from(1u64);
+
+		let Some((persisted_hash, _)) = store.get_block(candidate_number)? else { continue };
+
+		let candidate_ref = BlockReference::Number(candidate_number);
+		let remote_block = web3
+			.eth()
+			.block(BlockId::Number(BlockNumber::Number(candidate_number)))
+			.await?
+			.ok_or(FetchError::BlockNotFound(candidate_ref))?;
+		let remote_hash = remote_block.hash.ok_or(FetchError::BlockNotFound(candidate_ref))?;
+
+		if remote_hash == persisted_hash {
+			return Ok(candidate_number)
+		}
+	}
+
+	Err(FetchError::NoCommonAncestor)
+}
+
+/// Fetches every block in `[start, end]` (inclusive) into a queue, building
+/// the contiguous `BlockReference::Number` range `fetch_block_queue` expects.
+pub async fn fetch_block_range(
+	start: U64,
+	end: U64,
+	web3: Web3<WebSocket>,
+	pools: &[PoolConfig],
+	swap_event_signature: H256,
+	swap_event: Event,
+) -> Result<VecDeque<Block>, FetchError> {
+	let mut block_references = vec![];
+	let mut n = start;
+	while n <= end {
+		block_references.push(BlockReference::Number(n));
+		n = n + U64::from(1u64);
+	}
+
+	fetch_block_queue(block_references, web3, pools, swap_event_signature, swap_event).await
 }
 
 #[cfg(test)]
@@ -294,27 +507,36 @@ mod tests {
 	use super::*;
 
 	#[test]
-	fn test_u256_to_string() {
-		let m = U256::from_dec_str("1000000000000").unwrap();
-		assert_eq!(u256_to_string(m, 6), String::from("1000000.000000"));
-
-		let m = U256::from_dec_str("1000000000001").unwrap();
-		assert_eq!(u256_to_string(m, 6), String::from("1000000.000001"));
-
-		let m = U256::from_dec_str("1000000000001").unwrap();
-		assert_eq!(u256_to_string(m, 6), String::from("1000000.000001"));
-
-		let m = U256::from_dec_str("1000000000001").unwrap();
-		assert_eq!(u256_to_string(m, 2), String::from("10000000000.01"));
-
-		let m = U256::from_dec_str("1000000000001").unwrap();
-		assert_eq!(u256_to_string(m, 0), String::from("1000000000001.0"));
+	fn test_block_reference_require_canonical() {
+		assert!(!BlockReference::Number(U64::from(1u32)).require_canonical());
+		assert!(!BlockReference::Hash { hash: H256::random(), require_canonical: false }
+			.require_canonical());
+		assert!(BlockReference::Hash { hash: H256::random(), require_canonical: true }
+			.require_canonical());
+	}
 
-		let m = U256::from_dec_str("1000000000001").unwrap();
-		assert_eq!(u256_to_string(m, 13), String::from("0.1000000000001"));
+	#[test]
+	fn test_fetch_error_non_canonical_display() {
+		let err = FetchError::NonCanonical {
+			hash: H256::zero(),
+			number: U64::from(7u32),
+			canonical_hash: H256::repeat_byte(1),
+		};
+
+		let message = err.to_string();
+		assert!(message.contains("is no longer canonical"));
+		assert!(message.contains("block 7"));
+	}
 
-		let m = U256::from_dec_str("1000000000001").unwrap();
-		assert_eq!(u256_to_string(m, 15), String::from("0.001000000000001"));
+	#[test]
+	fn test_fetch_error_unexpected_hash_display() {
+		let requested = H256::repeat_byte(1);
+		let returned = H256::repeat_byte(2);
+		let err = FetchError::UnexpectedHash { requested, returned };
+
+		let message = err.to_string();
+		assert!(message.contains(&format!("{:#x}", requested)));
+		assert!(message.contains(&format!("{:#x}", returned)));
 	}
 
 	#[test]
@@ -335,13 +557,15 @@ mod tests {
 			Block { hash: H256::random(), number: U64::from(5u32), parsed_logs: vec![] },
 		]);
 
-		let reorganizations = check_and_update_queue(&mut queue, &new_queue);
-		assert_eq!(reorganizations, 4)
+		let outcome = check_and_update_queue(&mut queue, &new_queue);
+		match outcome {
+			ReorgOutcome::Shallow { depth, .. } => assert_eq!(depth, 4),
+			ReorgOutcome::DeeperThanTracked { .. } => panic!("expected a shallow reorg"),
+		}
 	}
 
 	#[test]
-	#[should_panic(expected = "A 5-blocks reorganization ocurred")]
-	fn test_check_and_update_queue_block_reorganization_5() {
+	fn test_check_and_update_queue_block_reorganization_5_is_deeper_than_tracked() {
 		let mut queue = VecDeque::<Block>::from(vec![
 			Block { hash: H256::random(), number: U64::from(5u32), parsed_logs: vec![] },
 			Block { hash: H256::random(), number: U64::from(4u32), parsed_logs: vec![] },
@@ -358,8 +582,8 @@ mod tests {
 			Block { hash: H256::random(), number: U64::from(1u32), parsed_logs: vec![] },
 		]);
 
-		let reorganizations = check_and_update_queue(&mut queue, &new_queue);
-		assert_eq!(reorganizations, 4)
+		let outcome = check_and_update_queue(&mut queue, &new_queue);
+		assert_eq!(outcome, ReorgOutcome::DeeperThanTracked { from_number: U64::from(5u32) });
 	}
 
 	#[test]
@@ -378,8 +602,11 @@ mod tests {
 			Block { hash: H256::random(), number: U64::from(3u32), parsed_logs: vec![] },
 		]);
 
-		let reorganizations = check_and_update_queue(&mut queue, &new_queue);
-		assert_eq!(reorganizations, 2)
+		let outcome = check_and_update_queue(&mut queue, &new_queue);
+		match outcome {
+			ReorgOutcome::Shallow { depth, .. } => assert_eq!(depth, 2),
+			ReorgOutcome::DeeperThanTracked { .. } => panic!("expected a shallow reorg"),
+		}
 	}
 
 	#[test]
@@ -430,11 +657,12 @@ mod tests {
 		]);
 
 		let parsed_logs = vec![ParsedLog {
+			pool_address: H160::random(),
 			sender: "0xuser".to_string(),
 			receiver: "0xreceiver".to_string(),
 			direction: "DAI -> USDC".to_string(),
-			amount_usdc: "1929.3939".to_string(),
-			amount_dai: "21921.20".to_string(),
+			amount0: "21921.20".to_string(),
+			amount1: "1929.3939".to_string(),
 		}];
 
 		let new_queue = VecDeque::<Block>::from(vec![
@@ -0,0 +1,94 @@
+//! Configuration for the Uniswap v3 pools this instance indexes, loaded
+//! from a file so one running instance can track several pools at once.
+
+use serde::Deserialize;
+use web3::types::H160;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PoolConfig {
+	pub address: H160,
+	pub token0_decimals: usize,
+	pub token1_decimals: usize,
+	pub token0_symbol: String,
+	pub token1_symbol: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PoolConfigFile {
+	pools: Vec<PoolConfig>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+	#[error("failed to read pool config file {path}: {source}")]
+	Io { path: String, source: std::io::Error },
+
+	#[error("failed to parse pool config file {path}: {source}")]
+	Parse { path: String, source: String },
+
+	#[error("pool config file {path} lists no pools")]
+	Empty { path: String },
+}
+
+/// Loads the list of pools to index from a TOML or JSON file, selected by
+/// the file extension (`.json` is parsed as JSON, anything else as TOML).
+pub fn load_pool_configs(path: &str) -> Result<Vec<PoolConfig>, ConfigError> {
+	let contents = std::fs::read_to_string(path)
+		.map_err(|source| ConfigError::Io { path: path.to_string(), source })?;
+
+	let file: PoolConfigFile = if path.ends_with(".json") {
+		serde_json::from_str(&contents)
+			.map_err(|source| ConfigError::Parse { path: path.to_string(), source: source.to_string() })?
+	} else {
+		toml::from_str(&contents)
+			.map_err(|source| ConfigError::Parse { path: path.to_string(), source: source.to_string() })?
+	};
+
+	if file.pools.is_empty() {
+		return Err(ConfigError::Empty { path: path.to_string() })
+	}
+
+	Ok(file.pools)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_load_pool_configs_toml() {
+		let dir = std::env::temp_dir();
+		let path = dir.join("pools_test.toml");
+		std::fs::write(
+			&path,
+			r#"
+			[[pools]]
+			address = "0x5777d92f208679db4b9778590fa3cab3ac9e2168"
+			token0_decimals = 18
+			token1_decimals = 6
+			token0_symbol = "DAI"
+			token1_symbol = "USDC"
+			"#,
+		)
+		.unwrap();
+
+		let pools = load_pool_configs(path.to_str().unwrap()).unwrap();
+		std::fs::remove_file(&path).ok();
+
+		assert_eq!(pools.len(), 1);
+		assert_eq!(pools[0].token0_symbol, "DAI");
+		assert_eq!(pools[0].token1_symbol, "USDC");
+	}
+
+	#[test]
+	fn test_load_pool_configs_rejects_empty_pool_list() {
+		let dir = std::env::temp_dir();
+		let path = dir.join("pools_test_empty.toml");
+		std::fs::write(&path, "pools = []\n").unwrap();
+
+		let result = load_pool_configs(path.to_str().unwrap());
+		std::fs::remove_file(&path).ok();
+
+		assert!(matches!(result, Err(ConfigError::Empty { .. })));
+	}
+}
@@ -0,0 +1,128 @@
+//! Persistence for blocks that have aged past `BLOCK_REORG_MAX_DEPTH` and
+//! been popped off the reorg queue, so a restart can resume from where it
+//! left off.
+
+use std::collections::BTreeMap;
+
+use web3::types::{H256, U64};
+
+use crate::ParsedLog;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+	#[error("storage backend error: {0}")]
+	Backend(String),
+
+	#[error("failed to (de)serialize a stored block: {0}")]
+	Serde(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct StoredBlock {
+	hash: H256,
+	parsed_logs: Vec<ParsedLog>,
+}
+
+pub trait Store {
+	fn put_block(&mut self, number: U64, hash: H256, parsed_logs: &[ParsedLog]) -> Result<(), StoreError>;
+	fn get_block(&self, number: U64) -> Result<Option<(H256, Vec<ParsedLog>)>, StoreError>;
+	fn highest_block_number(&self) -> Result<Option<U64>, StoreError>;
+}
+
+/// An in-memory `Store`, used in tests.
+#[derive(Default)]
+pub struct InMemoryStore {
+	blocks: BTreeMap<u64, StoredBlock>,
+}
+
+impl InMemoryStore {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+impl Store for InMemoryStore {
+	fn put_block(&mut self, number: U64, hash: H256, parsed_logs: &[ParsedLog]) -> Result<(), StoreError> {
+		self.blocks.insert(number.as_u64(), StoredBlock { hash, parsed_logs: parsed_logs.to_vec() });
+		Ok(())
+	}
+
+	fn get_block(&self, number: U64) -> Result<Option<(H256, Vec<ParsedLog>)>, StoreError> {
+		Ok(self.blocks.get(&number.as_u64()).map(|b| (b.hash, b.parsed_logs.clone())))
+	}
+
+	fn highest_block_number(&self) -> Result<Option<U64>, StoreError> {
+		Ok(self.blocks.keys().next_back().map(|&n| U64::from(n)))
+	}
+}
+
+/// A disk-backed `Store` built on `sled`, an embedded key-value store.
+pub struct SledStore {
+	db: sled::Db,
+}
+
+impl SledStore {
+	pub fn open(path: &str) -> Result<Self, StoreError> {
+		let db = sled::open(path).map_err(|e| StoreError::Backend(e.to_string()))?;
+		Ok(Self { db })
+	}
+}
+
+impl Store for SledStore {
+	fn put_block(&mut self, number: U64, hash: H256, parsed_logs: &[ParsedLog]) -> Result<(), StoreError> {
+		let key = number.as_u64().to_be_bytes();
+		let value = serde_json::to_vec(&StoredBlock { hash, parsed_logs: parsed_logs.to_vec() })?;
+		self.db.insert(key, value).map_err(|e| StoreError::Backend(e.to_string()))?;
+		Ok(())
+	}
+
+	fn get_block(&self, number: U64) -> Result<Option<(H256, Vec<ParsedLog>)>, StoreError> {
+		let key = number.as_u64().to_be_bytes();
+		let Some(value) = self.db.get(key).map_err(|e| StoreError::Backend(e.to_string()))? else {
+			return Ok(None)
+		};
+		let stored: StoredBlock = serde_json::from_slice(&value)?;
+		Ok(Some((stored.hash, stored.parsed_logs)))
+	}
+
+	fn highest_block_number(&self) -> Result<Option<U64>, StoreError> {
+		let Some((key, _)) = self.db.last().map_err(|e| StoreError::Backend(e.to_string()))? else {
+			return Ok(None)
+		};
+		let bytes: [u8; 8] = key.as_ref().try_into().map_err(|_| {
+			StoreError::Backend("stored key is not an 8-byte block number".to_string())
+		})?;
+		Ok(Some(U64::from(u64::from_be_bytes(bytes))))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_in_memory_store_round_trip() {
+		let mut store = InMemoryStore::new();
+		assert_eq!(store.highest_block_number().unwrap(), None);
+
+		store.put_block(U64::from(10u64), H256::random(), &[]).unwrap();
+		store.put_block(U64::from(11u64), H256::random(), &[]).unwrap();
+
+		assert_eq!(store.highest_block_number().unwrap(), Some(U64::from(11u64)));
+		assert!(store.get_block(U64::from(10u64)).unwrap().is_some());
+		assert!(store.get_block(U64::from(12u64)).unwrap().is_none());
+	}
+
+	#[test]
+	fn test_in_memory_store_overwrites_stale_entry() {
+		let mut store = InMemoryStore::new();
+		let stale_hash = H256::random();
+		let canonical_hash = H256::random();
+
+		store.put_block(U64::from(5u64), stale_hash, &[]).unwrap();
+		store.put_block(U64::from(5u64), canonical_hash, &[]).unwrap();
+
+		let (hash, _) = store.get_block(U64::from(5u64)).unwrap().unwrap();
+		assert_eq!(hash, canonical_hash);
+	}
+}
@@ -0,0 +1,164 @@
+//! Signed fixed-point formatting and parsing for on-chain token amounts,
+//! which are `U256` integers scaled by `10^decimals` with negative values
+//! encoded as two's complement.
+
+use std::fmt;
+
+use web3::types::U256;
+
+use crate::u256_is_negative;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+	Empty,
+	TooManyFractionalDigits { found: usize, decimals: usize },
+	InvalidDigit,
+	Overflow,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ParseError::Empty => write!(f, "amount string is empty"),
+			ParseError::TooManyFractionalDigits { found, decimals } => write!(
+				f,
+				"amount has {} fractional digits but only {} are allowed",
+				found, decimals
+			),
+			ParseError::InvalidDigit => write!(f, "amount contains a non-digit character"),
+			ParseError::Overflow => write!(f, "amount overflows U256 after scaling by decimals"),
+		}
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+/// Formats an unsigned fixed-point `amount` scaled by `10^decimals` as a
+/// decimal string, e.g. `format_units(1_000_000.into(), 6) == "1.000000"`.
+pub fn format_units(amount: U256, decimals: usize) -> String {
+	let decimal_string = amount.to_string();
+
+	if decimals == 0 {
+		return decimal_string
+	}
+
+	if decimal_string.len() > decimals {
+		let (integer, fraction) = decimal_string.split_at(decimal_string.len() - decimals);
+		format!("{}.{}", integer, fraction)
+	} else {
+		format!("0.{}{}", "0".repeat(decimals - decimal_string.len()), decimal_string)
+	}
+}
+
+/// Formats a signed fixed-point `amount`, treating the top bit as a sign
+/// (mirroring [`crate::u256_is_negative`]), prefixing a `-` for negative
+/// values.
+pub fn format_units_signed(amount: U256, decimals: usize) -> String {
+	if u256_is_negative(amount) {
+		format!("-{}", format_units(two_complement_negate(amount), decimals))
+	} else {
+		format_units(amount, decimals)
+	}
+}
+
+/// Parses a decimal string into a `U256` scaled by `10^decimals`, the inverse
+/// of [`format_units`]. Rejects strings with more fractional digits than
+/// `decimals` and overflowing values.
+pub fn parse_units(s: &str, decimals: usize) -> Result<U256, ParseError> {
+	if s.is_empty() {
+		return Err(ParseError::Empty)
+	}
+
+	let (integer_part, fraction_part) = match s.split_once('.') {
+		Some((integer, fraction)) => (integer, fraction),
+		None => (s, ""),
+	};
+
+	if fraction_part.len() > decimals {
+		return Err(ParseError::TooManyFractionalDigits { found: fraction_part.len(), decimals })
+	}
+
+	let integer_part = if integer_part.is_empty() { "0" } else { integer_part };
+
+	let padded_fraction = format!("{}{}", fraction_part, "0".repeat(decimals - fraction_part.len()));
+	let combined = format!("{}{}", integer_part, padded_fraction);
+
+	if combined.is_empty() || !combined.bytes().all(|b| b.is_ascii_digit()) {
+		return Err(ParseError::InvalidDigit)
+	}
+
+	U256::from_dec_str(&combined).map_err(|_| ParseError::Overflow)
+}
+
+fn two_complement_negate(amount: U256) -> U256 {
+	let mut bytes = [0u8; 32];
+	amount.to_big_endian(&mut bytes);
+
+	for b in bytes.iter_mut() {
+		*b = !(*b);
+	}
+
+	U256::from_big_endian(&bytes) + U256::one()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_format_units() {
+		let m = U256::from_dec_str("1000000000000").unwrap();
+		assert_eq!(format_units(m, 6), "1000000.000000");
+
+		let m = U256::from_dec_str("1000000000001").unwrap();
+		assert_eq!(format_units(m, 6), "1000000.000001");
+
+		let m = U256::from_dec_str("1000000000001").unwrap();
+		assert_eq!(format_units(m, 2), "10000000000.01");
+
+		let m = U256::from_dec_str("1000000000001").unwrap();
+		assert_eq!(format_units(m, 0), "1000000000001");
+
+		let m = U256::from_dec_str("1000000000001").unwrap();
+		assert_eq!(format_units(m, 13), "0.1000000000001");
+
+		let m = U256::from_dec_str("1000000000001").unwrap();
+		assert_eq!(format_units(m, 15), "0.001000000000001");
+	}
+
+	#[test]
+	fn test_parse_units_round_trip() {
+		let amounts = ["1000000.000000", "0.1000000000001", "0.001000000000001", "42"];
+		for amount in amounts {
+			let decimals = amount.split_once('.').map(|(_, f)| f.len()).unwrap_or(0);
+			let parsed = parse_units(amount, decimals).unwrap();
+			assert_eq!(format_units(parsed, decimals), amount);
+		}
+	}
+
+	#[test]
+	fn test_parse_units_missing_fraction() {
+		assert_eq!(parse_units("42", 6).unwrap(), U256::from_dec_str("42000000").unwrap());
+	}
+
+	#[test]
+	fn test_parse_units_rejects_too_many_fractional_digits() {
+		assert_eq!(
+			parse_units("1.2345", 2),
+			Err(ParseError::TooManyFractionalDigits { found: 4, decimals: 2 })
+		);
+	}
+
+	#[test]
+	fn test_parse_units_rejects_invalid_digit() {
+		assert_eq!(parse_units("12a.3", 2), Err(ParseError::InvalidDigit));
+	}
+
+	#[test]
+	fn test_format_units_signed_negative() {
+		let positive = U256::from_dec_str("1000000").unwrap();
+		let negative = two_complement_negate(positive);
+		assert_eq!(format_units_signed(negative, 6), "-1.000000");
+		assert_eq!(format_units_signed(positive, 6), "1.000000");
+	}
+}